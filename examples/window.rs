@@ -168,6 +168,43 @@ mod shader {
             }
         }
 
+        /// Like [`Self::new`], but takes the fragment shader source directly
+        /// instead of a file path — e.g. for `hapcodec::shaders::SCALED_YCOCG_FS`,
+        /// which ships as a string constant rather than a file on disk.
+        pub fn with_fragment_source(vertexPath: &str, fragmentSource: &str) -> Shader {
+            let mut shader = Shader { ID: 0 };
+            let mut vShaderFile =
+                File::open(vertexPath).unwrap_or_else(|_| panic!("Failed to open {}", vertexPath));
+            let mut vertexCode = String::new();
+            vShaderFile
+                .read_to_string(&mut vertexCode)
+                .expect("Failed to read vertex shader");
+
+            let vShaderCode = CString::new(vertexCode.as_bytes()).unwrap();
+            let fShaderCode = CString::new(fragmentSource.as_bytes()).unwrap();
+
+            unsafe {
+                let vertex = gl::CreateShader(gl::VERTEX_SHADER);
+                gl::ShaderSource(vertex, 1, &vShaderCode.as_ptr(), ptr::null());
+                gl::CompileShader(vertex);
+                shader.checkCompileErrors(vertex, "VERTEX");
+                let fragment = gl::CreateShader(gl::FRAGMENT_SHADER);
+                gl::ShaderSource(fragment, 1, &fShaderCode.as_ptr(), ptr::null());
+                gl::CompileShader(fragment);
+                shader.checkCompileErrors(fragment, "FRAGMENT");
+                let ID = gl::CreateProgram();
+                gl::AttachShader(ID, vertex);
+                gl::AttachShader(ID, fragment);
+                gl::LinkProgram(ID);
+                shader.checkCompileErrors(ID, "PROGRAM");
+                gl::DeleteShader(vertex);
+                gl::DeleteShader(fragment);
+                shader.ID = ID;
+            }
+
+            shader
+        }
+
         /// Only used in 4.9 Geometry shaders - ignore until then (shader.h in original C++)
         pub fn with_geometry_shader(
             vertexPath: &str,
@@ -274,10 +311,6 @@ async fn main() -> anyhow::Result<()> {
     gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
 
     let (our_shader, vbo, vao, ebo, texture) = unsafe {
-        // build and compile our shader program
-        // ------------------------------------
-        let our_shader = Shader::new("examples/shaders/texture.vs", "examples/shaders/texture.fs");
-
         // set up vertex data (and buffer(s)) and configure vertex attributes
         // ------------------------------------------------------------------
         // HINT: type annotation is crucial since default for float literals is f64
@@ -359,19 +392,39 @@ async fn main() -> anyhow::Result<()> {
         buf.resize(sample.size, 0);
         reader.read_exact(&mut buf).await?;
         let mut frame = std::io::Cursor::new(buf);
-        let frame = hapcodec::decode_frame(&mut frame)?;
+        let frame = hapcodec::Decoder::new().decode_frame(&mut frame)?;
         let GeneralSampleDescription::Hap1 { width, height, ..} = mp4.moov.traks[0].mdia.minf.stbl.stsd.sample_description_table[0] else {
             unimplemented!();
         };
 
-        let hapcodec::Texture::RGB_DXT1_BC1(raw) = frame else {
-            unimplemented!()
+        // Build and compile our shader program. Hap Q's scaled-YCoCg color
+        // plane needs the unpacking transform in `SCALED_YCOCG_FS`; every
+        // other variant samples directly, so the plain texture fragment
+        // shader is enough.
+        let our_shader = if matches!(frame, hapcodec::Texture::ScaledYCoCg_DXT5_BC3(_)) {
+            Shader::with_fragment_source(
+                "examples/shaders/texture.vs",
+                hapcodec::shaders::SCALED_YCOCG_FS,
+            )
+        } else {
+            Shader::new("examples/shaders/texture.vs", "examples/shaders/texture.fs")
         };
 
+        let internal_format = frame.gl_internal_format().unwrap_or_else(|| {
+            panic!("no single GL internal format for this Texture variant")
+        });
+        let byte_len = frame
+            .compressed_byte_len(width, height)
+            .expect("dual-plane textures need compressed_byte_len_per_plane instead");
+        let raw = frame
+            .get_single_texture_raw_data()
+            .expect("dual-plane textures need a separate upload per plane");
+        assert_eq!(raw.len(), byte_len);
+
         gl::CompressedTexImage2D(
             gl::TEXTURE_2D,
             0,
-            0x8c4c,
+            internal_format,
             width as i32,
             height as i32,
             0,
@@ -0,0 +1,698 @@
+use std::io::{self, Write};
+
+use byteorder::{WriteBytesExt, LE};
+
+use crate::{texture_format_nibble, Error, PixelCompression, SecondStageCompressor, Texture};
+
+const HAP_SECTION_CHUNK_SECOND_STAGE_COMPRESSOR_TABLE: u8 = 0x02;
+const HAP_SECTION_CHUNK_SIZE_TABLE: u8 = 0x03;
+const HAP_SECTION_CHUNK_OFFSET_TABLE: u8 = 0x04;
+const HAP_SECTION_DECODE_INSTRUCTIONS_CONTAINER: u8 = 0x00;
+const HAP_SECTION_MULTI_TEXTURE: u8 = 0x0d;
+
+const SECOND_STAGE_COMPRESSOR_NONE: u8 = 0x0A;
+const SECOND_STAGE_COMPRESSOR_SNAPPY: u8 = 0x0B;
+
+/// Controls how [`Encoder`] lays out the chunk table for a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// Number of independently Snappy-compressed chunks to split the payload
+    /// into. `1` (the default) produces the single-section fast path
+    /// (`0xA0`/`0xB0`) instead of the `0xC0` complex container.
+    pub chunk_count: usize,
+    /// Which second-stage compressor to use on each chunk.
+    /// [`SecondStageCompressor::Snappy`] (the default) Snappy-compresses and
+    /// keeps the compressed form only if it's smaller, falling back to
+    /// storing the chunk raw; [`SecondStageCompressor::None`] always stores
+    /// chunks raw, skipping compression entirely. `Complex` is not a valid
+    /// per-chunk choice and is rejected with [`Error::UnknownCompressor`].
+    pub second_stage_compressor: SecondStageCompressor,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            chunk_count: 1,
+            second_stage_compressor: SecondStageCompressor::Snappy,
+        }
+    }
+}
+
+fn write_section_header<W: Write>(w: &mut W, section_type: u8, size: usize) -> io::Result<()> {
+    if size < 0x00FF_FFFF {
+        w.write_u24::<LE>(size as u32)?;
+        w.write_u8(section_type)?;
+    } else {
+        // Zero short size is the sentinel for an 8-byte extended size field
+        // (see `parse_section_header`), used for sections too large for the
+        // 3-byte short form.
+        w.write_u24::<LE>(0)?;
+        w.write_u8(section_type)?;
+        w.write_u64::<LE>(size as u64)?;
+    }
+    Ok(())
+}
+
+/// Splits `raw` into `chunk_count` roughly equal, contiguous pieces. The last
+/// chunk absorbs any remainder so chunk sizes always sum to `raw.len()`.
+fn split_chunks(raw: &[u8], chunk_count: usize) -> Vec<&[u8]> {
+    let chunk_count = chunk_count.max(1).min(raw.len().max(1));
+    let base = raw.len() / chunk_count;
+    let mut chunks = Vec::with_capacity(chunk_count);
+    let mut offset = 0;
+    for i in 0..chunk_count {
+        let len = if i + 1 == chunk_count {
+            raw.len() - offset
+        } else {
+            base
+        };
+        chunks.push(&raw[offset..offset + len]);
+        offset += len;
+    }
+    chunks
+}
+
+/// Compresses `chunk` per `compressor`. `Snappy` keeps the compressed form
+/// only if it is smaller than the input, falling back to storing it raw.
+/// Returns `(compressor_byte, stored_bytes)`.
+fn compress_chunk(
+    chunk: &[u8],
+    compressor: SecondStageCompressor,
+) -> Result<(u8, Vec<u8>), Error> {
+    match compressor {
+        SecondStageCompressor::None => Ok((SECOND_STAGE_COMPRESSOR_NONE, chunk.to_vec())),
+        SecondStageCompressor::Snappy => {
+            let compressed = snap::raw::Encoder::new()
+                .compress_vec(chunk)
+                .map_err(Error::Snappy)?;
+            if compressed.len() < chunk.len() {
+                Ok((SECOND_STAGE_COMPRESSOR_SNAPPY, compressed))
+            } else {
+                Ok((SECOND_STAGE_COMPRESSOR_NONE, chunk.to_vec()))
+            }
+        }
+        SecondStageCompressor::Complex => Err(Error::UnknownCompressor(compressor as u8)),
+    }
+}
+
+fn compression_format_nibble(compression: PixelCompression) -> Result<u8, Error> {
+    match compression {
+        PixelCompression::DXT1BC1 => Ok(0x0b),
+        PixelCompression::DXT5BC3 => Ok(0x0e),
+        PixelCompression::ScaledYCoCgDXT5BC3 => Ok(0x0f),
+        unsupported => Err(Error::UnknownCompressor(unsupported as u8)),
+    }
+}
+
+/// A pluggable block-compression backend for
+/// [`Encoder::encode_rgba_with`], so callers can swap in a faster
+/// implementation (e.g. `squish`, `intel-tex-rs`) without touching the
+/// container-format code in this module.
+pub trait BlockCompressor {
+    fn compress(
+        &self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        compression: PixelCompression,
+    ) -> Result<Vec<u8>, Error>;
+}
+
+/// The scalar, dependency-free [`BlockCompressor`] used by
+/// [`Encoder::encode_rgba`]: [`compress_dxt1`], [`compress_dxt5`] and
+/// [`compress_scaled_ycocg_dxt5`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScalarBlockCompressor;
+
+impl BlockCompressor for ScalarBlockCompressor {
+    fn compress(
+        &self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        compression: PixelCompression,
+    ) -> Result<Vec<u8>, Error> {
+        match compression {
+            PixelCompression::DXT1BC1 => Ok(compress_dxt1(pixels, width, height)),
+            PixelCompression::DXT5BC3 => Ok(compress_dxt5(pixels, width, height)),
+            PixelCompression::ScaledYCoCgDXT5BC3 => {
+                Ok(compress_scaled_ycocg_dxt5(pixels, width, height))
+            }
+            unsupported => Err(Error::UnknownCompressor(unsupported as u8)),
+        }
+    }
+}
+
+/// Encodes [`Texture`]s (already block-compressed, e.g. by a GPU) into Hap
+/// frame bytes. This is the natural inverse of
+/// [`crate::Decoder::decode_frame`]/[`crate::Decoder::decode_texture`]: it
+/// writes the top-level section header, the `0xC0` complex path with its
+/// second-stage-compressor table (`0x02`), chunk size table (`0x03`) and
+/// chunk offset table (`0x04`) when asked for more than one chunk, and the
+/// single-section `0xA0`/`0xB0` fast paths otherwise.
+pub struct Encoder;
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Encodes a full Hap frame (top-level section included) for `texture`.
+    pub fn encode_frame(&self, texture: &Texture, opts: &EncodeOptions) -> Result<Vec<u8>, Error> {
+        match texture {
+            // Plain Hap Alpha: unscaled RGBA color plane (see the doc comment
+            // on `Texture::ScaledYCoCgWithAlpha`), so the color-plane nibble matches
+            // `RGBA_DXT5_BC3`'s `0x0e`, not the scaled-YCoCg `0x0f` — using
+            // `0x0f` here would make `Decoder::decode_frame` misclassify the
+            // round-tripped frame as Hap Q Alpha.
+            Texture::MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1(color, alpha) => {
+                self.encode_multiple_images(0x0e, color, alpha, opts)
+            }
+            // Hap Q Alpha: scaled-YCoCg color plane, nibble `0x0f`.
+            Texture::ScaledYCoCgWithAlpha { color, alpha } => {
+                self.encode_multiple_images(0x0f, color, alpha, opts)
+            }
+            _ => {
+                let format = texture_format_nibble(texture)?;
+                let raw = texture
+                    .get_single_texture_raw_data_ref()
+                    .expect("non-multi-image Texture always carries single raw data");
+                self.encode_single_texture(format, raw, opts)
+            }
+        }
+    }
+
+    /// Encodes raw RGBA8 pixels by first block-compressing them to
+    /// `compression` with [`ScalarBlockCompressor`], then wrapping the
+    /// result in a Hap frame.
+    pub fn encode_rgba(
+        &self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        compression: PixelCompression,
+        opts: &EncodeOptions,
+    ) -> Result<Vec<u8>, Error> {
+        self.encode_rgba_with(
+            &ScalarBlockCompressor,
+            pixels,
+            width,
+            height,
+            compression,
+            opts,
+        )
+    }
+
+    /// Like [`Encoder::encode_rgba`], but runs block compression through
+    /// `compressor` instead of the built-in [`ScalarBlockCompressor`].
+    pub fn encode_rgba_with(
+        &self,
+        compressor: &impl BlockCompressor,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        compression: PixelCompression,
+        opts: &EncodeOptions,
+    ) -> Result<Vec<u8>, Error> {
+        let format = compression_format_nibble(compression)?;
+        let raw = compressor.compress(pixels, width, height, compression)?;
+        self.encode_single_texture(format, &raw, opts)
+    }
+
+    /// Like [`Encoder::encode_rgba`], but runs the block-compression step on
+    /// the GPU via `gpu` instead of the CPU compressors in this module.
+    /// [`crate::GpuCompressor`] implements [`BlockCompressor`], so this is
+    /// just [`Encoder::encode_rgba_with`] with the format nibble restricted
+    /// to what the GPU path currently supports (BC1 only — see
+    /// `GpuCompressor::compile`).
+    #[cfg(feature = "wgpu")]
+    pub fn encode_rgba_gpu(
+        &self,
+        gpu: &crate::GpuCompressor,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        compression: PixelCompression,
+        opts: &EncodeOptions,
+    ) -> Result<Vec<u8>, Error> {
+        if !matches!(compression, PixelCompression::DXT1BC1) {
+            return Err(Error::UnknownCompressor(compression as u8));
+        }
+        self.encode_rgba_with(gpu, pixels, width, height, compression, opts)
+    }
+
+    /// Builds the two-subsection `0x0d` container used by
+    /// `MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1` (plain Hap Alpha,
+    /// `color_format` `0x0e`) and `ScaledYCoCgWithAlpha` (Hap Q Alpha,
+    /// `color_format` `0x0f`). `color_format` is a parameter rather than
+    /// hardcoded so each call site supplies the nibble matching its own
+    /// color-plane encoding — `Decoder::decode_frame` tells the two variants
+    /// apart by reading this exact nibble back, so getting it wrong here
+    /// would silently misclassify the frame on decode.
+    fn encode_multiple_images(
+        &self,
+        color_format: u8,
+        color: &[u8],
+        alpha: &[u8],
+        opts: &EncodeOptions,
+    ) -> Result<Vec<u8>, Error> {
+        let mut body = self.encode_single_texture(color_format, color, opts)?;
+        body.append(&mut self.encode_single_texture(0x01, alpha, opts)?);
+        let mut out = Vec::with_capacity(body.len() + 8);
+        write_section_header(&mut out, HAP_SECTION_MULTI_TEXTURE, body.len())?;
+        out.append(&mut body);
+        Ok(out)
+    }
+
+    /// Encodes a single texture section (header + payload) for `raw` bytes
+    /// already in `format`'s block-compressed layout.
+    fn encode_single_texture(
+        &self,
+        format: u8,
+        raw: &[u8],
+        opts: &EncodeOptions,
+    ) -> Result<Vec<u8>, Error> {
+        if opts.chunk_count <= 1 {
+            self.encode_single_chunk(format, raw, opts.second_stage_compressor)
+        } else {
+            self.encode_complex(format, raw, opts.chunk_count, opts.second_stage_compressor)
+        }
+    }
+
+    /// `0xA0`/`0xB0` fast path: the whole texture as one chunk, stored
+    /// uncompressed or Snappy-compressed.
+    fn encode_single_chunk(
+        &self,
+        format: u8,
+        raw: &[u8],
+        second_stage_compressor: SecondStageCompressor,
+    ) -> Result<Vec<u8>, Error> {
+        let (compressor, payload) = compress_chunk(raw, second_stage_compressor)?;
+        let section_type = if compressor == SECOND_STAGE_COMPRESSOR_SNAPPY {
+            0xB0 | format
+        } else {
+            0xA0 | format
+        };
+        let mut out = Vec::with_capacity(payload.len() + 8);
+        write_section_header(&mut out, section_type, payload.len())?;
+        out.extend_from_slice(&payload);
+        Ok(out)
+    }
+
+    /// `0xC0` complex path: splits `raw` into `chunk_count` chunks, each
+    /// independently compressed per `second_stage_compressor`, preceded by
+    /// the decode-instructions container.
+    fn encode_complex(
+        &self,
+        format: u8,
+        raw: &[u8],
+        chunk_count: usize,
+        second_stage_compressor: SecondStageCompressor,
+    ) -> Result<Vec<u8>, Error> {
+        let chunks = split_chunks(raw, chunk_count)
+            .into_iter()
+            .map(|chunk| compress_chunk(chunk, second_stage_compressor))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let compressors: Vec<u8> = chunks.iter().map(|(c, _)| *c).collect();
+        let mut size_table = Vec::with_capacity(chunks.len() * 4);
+        let mut offset_table = Vec::with_capacity(chunks.len() * 4);
+        let mut chunk_data = Vec::new();
+        let mut offset = 0u32;
+        for (_, payload) in &chunks {
+            size_table.write_u32::<LE>(payload.len() as u32)?;
+            offset_table.write_u32::<LE>(offset)?;
+            offset += payload.len() as u32;
+            chunk_data.extend_from_slice(payload);
+        }
+
+        let mut container = Vec::new();
+        write_section_header(
+            &mut container,
+            HAP_SECTION_CHUNK_SECOND_STAGE_COMPRESSOR_TABLE,
+            compressors.len(),
+        )?;
+        container.extend_from_slice(&compressors);
+        write_section_header(
+            &mut container,
+            HAP_SECTION_CHUNK_SIZE_TABLE,
+            size_table.len(),
+        )?;
+        container.extend_from_slice(&size_table);
+        write_section_header(
+            &mut container,
+            HAP_SECTION_CHUNK_OFFSET_TABLE,
+            offset_table.len(),
+        )?;
+        container.extend_from_slice(&offset_table);
+
+        let mut instructions = Vec::with_capacity(container.len() + 4);
+        write_section_header(
+            &mut instructions,
+            HAP_SECTION_DECODE_INSTRUCTIONS_CONTAINER,
+            container.len(),
+        )?;
+        instructions.extend_from_slice(&container);
+
+        let mut out = Vec::with_capacity(instructions.len() + chunk_data.len() + 8);
+        write_section_header(
+            &mut out,
+            0xC0 | format,
+            instructions.len() + chunk_data.len(),
+        )?;
+        out.extend_from_slice(&instructions);
+        out.extend_from_slice(&chunk_data);
+        Ok(out)
+    }
+}
+
+type Rgba = [u8; 4];
+
+/// Reads the 4x4 pixel block at `(bx, by)` (in block coordinates), clamping
+/// to the last valid row/column when `width`/`height` aren't multiples of 4.
+fn gather_block(pixels: &[u8], width: u32, height: u32, bx: u32, by: u32) -> [Rgba; 16] {
+    let mut block = [[0u8; 4]; 16];
+    for row in 0..4 {
+        let y = (by * 4 + row).min(height - 1);
+        for col in 0..4 {
+            let x = (bx * 4 + col).min(width - 1);
+            let idx = (y * width + x) as usize * 4;
+            block[(row * 4 + col) as usize].copy_from_slice(&pixels[idx..idx + 4]);
+        }
+    }
+    block
+}
+
+fn to_rgb565(pixel: Rgba) -> u16 {
+    let r = (pixel[0] as u16 * 31 + 127) / 255;
+    let g = (pixel[1] as u16 * 63 + 127) / 255;
+    let b = (pixel[2] as u16 * 31 + 127) / 255;
+    (r << 11) | (g << 5) | b
+}
+
+fn rgb565_to_rgb(color: u16) -> (u8, u8, u8) {
+    let r = ((color >> 11) & 0x1F) as u8;
+    let g = ((color >> 5) & 0x3F) as u8;
+    let b = (color & 0x1F) as u8;
+    (
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    )
+}
+
+/// Compresses one 4x4 block to 8-byte DXT1/BC1 data: min/max RGB corners
+/// quantized to RGB565 as `color0`/`color1`, a 4-entry palette, and a 2-bit
+/// nearest-palette index per pixel.
+fn compress_block_dxt1(block: &[Rgba; 16]) -> [u8; 8] {
+    let mut min = [255u8; 3];
+    let mut max = [0u8; 3];
+    for pixel in block {
+        for c in 0..3 {
+            min[c] = min[c].min(pixel[c]);
+            max[c] = max[c].max(pixel[c]);
+        }
+    }
+    let color0 = to_rgb565([max[0], max[1], max[2], 255]);
+    let color1 = to_rgb565([min[0], min[1], min[2], 255]);
+
+    let (r0, g0, b0) = rgb565_to_rgb(color0);
+    let (r1, g1, b1) = rgb565_to_rgb(color1);
+    let palette: [(u8, u8, u8); 4] = [
+        (r0, g0, b0),
+        (r1, g1, b1),
+        (
+            ((2 * r0 as u16 + r1 as u16) / 3) as u8,
+            ((2 * g0 as u16 + g1 as u16) / 3) as u8,
+            ((2 * b0 as u16 + b1 as u16) / 3) as u8,
+        ),
+        (
+            ((r0 as u16 + 2 * r1 as u16) / 3) as u8,
+            ((g0 as u16 + 2 * g1 as u16) / 3) as u8,
+            ((b0 as u16 + 2 * b1 as u16) / 3) as u8,
+        ),
+    ];
+
+    let mut indices = 0u32;
+    for (i, pixel) in block.iter().enumerate() {
+        let mut best = 0u32;
+        let mut best_dist = u32::MAX;
+        for (idx, candidate) in palette.iter().enumerate() {
+            let dist = (pixel[0] as i32 - candidate.0 as i32).pow(2) as u32
+                + (pixel[1] as i32 - candidate.1 as i32).pow(2) as u32
+                + (pixel[2] as i32 - candidate.2 as i32).pow(2) as u32;
+            if dist < best_dist {
+                best_dist = dist;
+                best = idx as u32;
+            }
+        }
+        indices |= best << (i * 2);
+    }
+
+    let mut out = [0u8; 8];
+    out[0..2].copy_from_slice(&color0.to_le_bytes());
+    out[2..4].copy_from_slice(&color1.to_le_bytes());
+    out[4..8].copy_from_slice(&indices.to_le_bytes());
+    out
+}
+
+/// Compresses one 4x4 block's alpha channel to the 8-byte DXT5/BC3 alpha
+/// block: two 8-bit endpoints (max/min alpha) followed by 3-bit indices into
+/// the 8-value interpolated ramp.
+fn compress_block_alpha(block: &[Rgba; 16]) -> [u8; 8] {
+    let mut min = 255u8;
+    let mut max = 0u8;
+    for pixel in block {
+        min = min.min(pixel[3]);
+        max = max.max(pixel[3]);
+    }
+
+    let ramp: [u8; 8] = if max > min {
+        [
+            max,
+            min,
+            ((6 * max as u16 + 1 * min as u16) / 7) as u8,
+            ((5 * max as u16 + 2 * min as u16) / 7) as u8,
+            ((4 * max as u16 + 3 * min as u16) / 7) as u8,
+            ((3 * max as u16 + 4 * min as u16) / 7) as u8,
+            ((2 * max as u16 + 5 * min as u16) / 7) as u8,
+            ((1 * max as u16 + 6 * min as u16) / 7) as u8,
+        ]
+    } else {
+        [max; 8]
+    };
+
+    let mut bits: u64 = 0;
+    for (i, pixel) in block.iter().enumerate() {
+        let mut best = 0u64;
+        let mut best_dist = u32::MAX;
+        for (idx, candidate) in ramp.iter().enumerate() {
+            let dist = (pixel[3] as i32 - *candidate as i32).unsigned_abs();
+            if dist < best_dist {
+                best_dist = dist;
+                best = idx as u64;
+            }
+        }
+        bits |= best << (i * 3);
+    }
+
+    let mut out = [0u8; 8];
+    out[0] = max;
+    out[1] = min;
+    out[2..8].copy_from_slice(&bits.to_le_bytes()[0..6]);
+    out
+}
+
+fn blocks_in(width: u32, height: u32) -> (u32, u32) {
+    ((width + 3) / 4, (height + 3) / 4)
+}
+
+/// Compresses `width`x`height` RGBA8 pixels to DXT1/BC1.
+pub fn compress_dxt1(pixels: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let (bw, bh) = blocks_in(width, height);
+    let mut out = Vec::with_capacity((bw * bh * 8) as usize);
+    for by in 0..bh {
+        for bx in 0..bw {
+            let block = gather_block(pixels, width, height, bx, by);
+            out.extend_from_slice(&compress_block_dxt1(&block));
+        }
+    }
+    out
+}
+
+/// Compresses `width`x`height` RGBA8 pixels to DXT5/BC3 (an 8-byte alpha
+/// block followed by the DXT1-style color block, per block).
+pub fn compress_dxt5(pixels: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let (bw, bh) = blocks_in(width, height);
+    let mut out = Vec::with_capacity((bw * bh * 16) as usize);
+    for by in 0..bh {
+        for bx in 0..bw {
+            let block = gather_block(pixels, width, height, bx, by);
+            out.extend_from_slice(&compress_block_alpha(&block));
+            out.extend_from_slice(&compress_block_dxt1(&block));
+        }
+    }
+    out
+}
+
+/// Packs RGBA8 `pixels` into the scaled-YCoCg layout unpacked by
+/// [`crate::shaders::SCALED_YCOCG_FS`]: per 4x4 block, `Y` goes to alpha and
+/// `Co`/`Cg` are scaled by a per-block factor (packed into blue, at the same
+/// 5-bit precision DXT5/BC3's color block already gives that channel) to use
+/// as much of the red/green channels' range as the block allows before DXT5
+/// quantizes them further.
+fn pack_scaled_ycocg(pixels: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let (bw, bh) = blocks_in(width, height);
+    let mut packed = vec![0u8; pixels.len()];
+    for by in 0..bh {
+        for bx in 0..bw {
+            let mut y = [0f32; 16];
+            let mut co = [0f32; 16];
+            let mut cg = [0f32; 16];
+            let mut max_chroma = 0f32;
+            for row in 0..4 {
+                for col in 0..4 {
+                    let x = (bx * 4 + col).min(width - 1);
+                    let py = (by * 4 + row).min(height - 1);
+                    let idx = (py * width + x) as usize * 4;
+                    let r = pixels[idx] as f32 / 255.0;
+                    let g = pixels[idx + 1] as f32 / 255.0;
+                    let b = pixels[idx + 2] as f32 / 255.0;
+                    let i = (row * 4 + col) as usize;
+                    // Inverse of the R/G/B reconstruction in
+                    // `crate::shaders::SCALED_YCOCG_FS`.
+                    y[i] = 0.25 * r + 0.5 * g + 0.25 * b;
+                    co[i] = (r - b) / 2.0;
+                    cg[i] = (2.0 * g - r - b) / 4.0;
+                    max_chroma = max_chroma.max(co[i].abs()).max(cg[i].abs());
+                }
+            }
+            // `s` must keep `co * s + 0.5` and `cg * s + 0.5` inside [0, 1];
+            // pick the largest such `s` so the 8-bit R/G channels use as much
+            // of their range as this block's chroma allows.
+            let s = if max_chroma > 0.0 {
+                (0.5 / max_chroma).min(32.875).max(1.0)
+            } else {
+                1.0
+            };
+            let scale_byte = (((s - 1.0) * 8.0).round().clamp(0.0, 255.0)) as u8;
+            for row in 0..4 {
+                for col in 0..4 {
+                    let x = bx * 4 + col;
+                    let py = by * 4 + row;
+                    if x >= width || py >= height {
+                        continue;
+                    }
+                    let idx = (py * width + x) as usize * 4;
+                    let i = (row * 4 + col) as usize;
+                    packed[idx] = ((co[i] * s + 0.5) * 255.0).round().clamp(0.0, 255.0) as u8;
+                    packed[idx + 1] = ((cg[i] * s + 0.5) * 255.0).round().clamp(0.0, 255.0) as u8;
+                    packed[idx + 2] = scale_byte;
+                    packed[idx + 3] = (y[i] * 255.0).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+    }
+    packed
+}
+
+/// Compresses `width`x`height` RGBA8 pixels to Hap Q's scaled-YCoCg DXT5/BC3
+/// layout (format nibble `0x0f`). See [`pack_scaled_ycocg`] for the
+/// color-space transform; the packed result is then block-compressed exactly
+/// like [`compress_dxt5`].
+pub fn compress_scaled_ycocg_dxt5(pixels: &[u8], width: u32, height: u32) -> Vec<u8> {
+    compress_dxt5(&pack_scaled_ycocg(pixels, width, height), width, height)
+}
+
+/// Encodes raw RGBA8 `pixels` to a full Hap frame using
+/// [`ScalarBlockCompressor`] — a convenience wrapper around
+/// `Encoder::new().encode_rgba(..)` for callers that don't need to hold onto
+/// an [`Encoder`].
+pub fn encode_frame(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    compression: PixelCompression,
+    opts: &EncodeOptions,
+) -> Result<Vec<u8>, Error> {
+    Encoder::new().encode_rgba(pixels, width, height, compression, opts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `gather_block` clamps out-of-range rows/columns to the last valid
+    /// row/column, so a non-multiple-of-4 image should still produce the
+    /// right block count and every block should be built only from pixels
+    /// that actually exist in `pixels` (no out-of-bounds read/panic).
+    #[test]
+    fn compress_dxt1_handles_non_4_aligned_dimensions() {
+        let width = 5;
+        let height = 5;
+        let pixels: Vec<u8> = (0..width * height)
+            .flat_map(|i| [i as u8, (i * 2) as u8, (i * 3) as u8, 255])
+            .collect();
+
+        let compressed = compress_dxt1(&pixels, width, height);
+
+        let (bw, bh) = blocks_in(width, height);
+        assert_eq!(bw, 2);
+        assert_eq!(bh, 2);
+        assert_eq!(compressed.len(), (bw * bh * 8) as usize);
+    }
+
+    /// Same non-4-aligned check for DXT5, whose blocks are twice as large
+    /// (alpha block + color block).
+    #[test]
+    fn compress_dxt5_handles_non_4_aligned_dimensions() {
+        let width = 6;
+        let height = 3;
+        let pixels: Vec<u8> = (0..width * height)
+            .flat_map(|i| [i as u8, (i * 2) as u8, (i * 3) as u8, (i * 4) as u8])
+            .collect();
+
+        let compressed = compress_dxt5(&pixels, width, height);
+
+        let (bw, bh) = blocks_in(width, height);
+        assert_eq!(bw, 2);
+        assert_eq!(bh, 1);
+        assert_eq!(compressed.len(), (bw * bh * 16) as usize);
+    }
+
+    /// A flat-color (min == max) block has no spread for `compress_block_dxt1`
+    /// to quantize: `color0`/`color1` should both come out as the same
+    /// RGB565 value, and every pixel should land on palette index 0 since
+    /// all four palette entries are identical and index 0 is checked first.
+    #[test]
+    fn compress_block_dxt1_handles_flat_color_block() {
+        let block: [Rgba; 16] = [[100, 150, 200, 255]; 16];
+
+        let out = compress_block_dxt1(&block);
+        let color0 = u16::from_le_bytes([out[0], out[1]]);
+        let color1 = u16::from_le_bytes([out[2], out[3]]);
+        let indices = u32::from_le_bytes([out[4], out[5], out[6], out[7]]);
+
+        assert_eq!(color0, color1);
+        assert_eq!(color0, to_rgb565([100, 150, 200, 255]));
+        assert_eq!(indices, 0);
+    }
+
+    /// Same degenerate case for the alpha block: a flat alpha channel has no
+    /// min/max spread, so `compress_block_alpha` should skip the ramp
+    /// entirely (the `max > min` branch), fill the ramp with the single flat
+    /// value, and assign every pixel index 0.
+    #[test]
+    fn compress_block_alpha_handles_flat_alpha_block() {
+        let block: [Rgba; 16] = [[10, 20, 30, 128]; 16];
+
+        let out = compress_block_alpha(&block);
+        let bits = u64::from_le_bytes([
+            out[2], out[3], out[4], out[5], out[6], out[7], 0, 0,
+        ]);
+
+        assert_eq!(out[0], 128);
+        assert_eq!(out[1], 128);
+        assert_eq!(bits, 0);
+    }
+}
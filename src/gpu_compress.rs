@@ -0,0 +1,331 @@
+//! GPU compute-based block compression for [`crate::Encoder`], gated behind
+//! the `wgpu` feature: instead of the scalar CPU compressors in
+//! [`crate::encoder`], the source RGBA image is uploaded as a sampled
+//! texture and a compute shader dispatches one workgroup thread per 4x4
+//! block, writing 8-byte BC1 block outputs into a storage buffer that's read
+//! back and handed to the Snappy chunking stage. BC1 is the only format with
+//! a compute shader here; every other [`PixelCompression`] is rejected with
+//! [`Error::UnknownCompressor`].
+//! This is the same approach as Godot's Betsy GPU BC1 compressor, and speeds
+//! up real-time encoding of video-resolution frames considerably over the
+//! CPU path.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use wgpu::util::DeviceExt;
+
+use crate::encoder::BlockCompressor;
+use crate::{Error, PixelCompression};
+
+const WORKGROUP_SIZE: u32 = 8;
+
+/// BC1/DXT1 compute shader: one invocation per 4x4 block, writing 8 bytes
+/// per block using the same min/max-RGB565 endpoint scheme as the CPU
+/// compressor in [`crate::encoder`].
+const BC1_COMPUTE_SHADER: &str = r#"
+@group(0) @binding(0) var src: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read_write> out_blocks: array<u32>;
+
+fn to_rgb565(c: vec3<f32>) -> u32 {
+    let r = u32(round(c.r * 31.0));
+    let g = u32(round(c.g * 63.0));
+    let b = u32(round(c.b * 31.0));
+    return (r << 11u) | (g << 5u) | b;
+}
+
+fn dist2(a: vec3<f32>, b: vec3<f32>) -> f32 {
+    let d = a - b;
+    return dot(d, d);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let dims = textureDimensions(src);
+    let blocks_x = (dims.x + 3u) / 4u;
+    let blocks_y = (dims.y + 3u) / 4u;
+    if (gid.x >= blocks_x || gid.y >= blocks_y) {
+        return;
+    }
+
+    var min_c = vec3<f32>(1.0, 1.0, 1.0);
+    var max_c = vec3<f32>(0.0, 0.0, 0.0);
+    var texels: array<vec3<f32>, 16>;
+    for (var row = 0u; row < 4u; row = row + 1u) {
+        for (var col = 0u; col < 4u; col = col + 1u) {
+            let x = min(gid.x * 4u + col, dims.x - 1u);
+            let y = min(gid.y * 4u + row, dims.y - 1u);
+            let texel = textureLoad(src, vec2<i32>(i32(x), i32(y)), 0).rgb;
+            texels[row * 4u + col] = texel;
+            min_c = min(min_c, texel);
+            max_c = max(max_c, texel);
+        }
+    }
+
+    let color0 = to_rgb565(max_c);
+    let color1 = to_rgb565(min_c);
+
+    // Same 4-entry palette layout (the two endpoints plus their 1/3 and 2/3
+    // interpolants) as the CPU compressor in
+    // `crate::encoder::compress_block_dxt1`, but built from the unquantized
+    // min/max colors rather than the RGB565-expanded endpoints, so the
+    // chosen indices can differ from the CPU compressor's.
+    let palette1 = max_c;
+    let palette2 = min_c;
+    let palette3 = (2.0 * max_c + min_c) / 3.0;
+    let palette4 = (max_c + 2.0 * min_c) / 3.0;
+
+    var indices = 0u;
+    for (var i = 0u; i < 16u; i = i + 1u) {
+        let texel = texels[i];
+        var best = 0u;
+        var best_dist = dist2(texel, palette1);
+        let d2 = dist2(texel, palette2);
+        if (d2 < best_dist) {
+            best = 1u;
+            best_dist = d2;
+        }
+        let d3 = dist2(texel, palette3);
+        if (d3 < best_dist) {
+            best = 2u;
+            best_dist = d3;
+        }
+        let d4 = dist2(texel, palette4);
+        if (d4 < best_dist) {
+            best = 3u;
+            best_dist = d4;
+        }
+        indices = indices | (best << (i * 2u));
+    }
+
+    let block_index = gid.y * blocks_x + gid.x;
+    out_blocks[block_index * 2u] = color0 | (color1 << 16u);
+    out_blocks[block_index * 2u + 1u] = indices;
+}
+"#;
+
+/// A cached compute pipeline and its bind group layout for one target
+/// format, so repeated frame encodes don't rebuild shaders.
+struct CompiledPipeline {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bytes_per_block: u32,
+}
+
+/// Runs block compression on the GPU via `wgpu` compute shaders, caching
+/// pipelines per [`PixelCompression`]. The cache is behind a [`RefCell`]
+/// rather than taking `&mut self` so that [`GpuCompressor`] can implement
+/// [`BlockCompressor`] (whose `compress` takes `&self`) and be passed to
+/// [`crate::Encoder::encode_rgba_with`] like any other backend; a GPU
+/// command encoder submit isn't reentrant, so there's no concurrent access
+/// to race.
+pub struct GpuCompressor {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipelines: RefCell<HashMap<PixelCompression, CompiledPipeline>>,
+}
+
+impl GpuCompressor {
+    pub fn new(device: wgpu::Device, queue: wgpu::Queue) -> Self {
+        Self {
+            device,
+            queue,
+            pipelines: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn ensure_pipeline(&self, compression: PixelCompression) -> Result<(), Error> {
+        if !self.pipelines.borrow().contains_key(&compression) {
+            let compiled = self.compile(compression)?;
+            self.pipelines.borrow_mut().insert(compression, compiled);
+        }
+        Ok(())
+    }
+
+    fn compile(&self, compression: PixelCompression) -> Result<CompiledPipeline, Error> {
+        let (source, bytes_per_block) = match compression {
+            PixelCompression::DXT1BC1 => (BC1_COMPUTE_SHADER, 8),
+            // Only BC1 has a compute shader; BC3/BC7 and every other format
+            // return an error here rather than running on the CPU, so
+            // callers that need them use the CPU compressors directly.
+            unsupported => return Err(Error::UnknownCompressor(unsupported as u8)),
+        };
+
+        let shader = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("hapcodec bc compress"),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+
+        let bind_group_layout =
+            self.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("hapcodec bc compress bind group layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+
+        let pipeline_layout = self
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("hapcodec bc compress pipeline layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let pipeline = self
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("hapcodec bc compress pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: "main",
+            });
+
+        Ok(CompiledPipeline {
+            pipeline,
+            bind_group_layout,
+            bytes_per_block,
+        })
+    }
+
+    /// Compresses `width`x`height` RGBA8 `pixels` to `compression` on the
+    /// GPU, returning the same byte layout as the CPU compressors in
+    /// [`crate::encoder`].
+    pub fn compress(
+        &self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        compression: PixelCompression,
+    ) -> Result<Vec<u8>, Error> {
+        self.ensure_pipeline(compression)?;
+        let pipelines = self.pipelines.borrow();
+        let CompiledPipeline {
+            pipeline,
+            bind_group_layout,
+            bytes_per_block,
+        } = pipelines.get(&compression).unwrap();
+
+        let blocks_x = width.div_ceil(4);
+        let blocks_y = height.div_ceil(4);
+        let output_size = (blocks_x * blocks_y * *bytes_per_block) as u64;
+
+        let texture = self.device.create_texture_with_data(
+            &self.queue,
+            &wgpu::TextureDescriptor {
+                label: Some("hapcodec source texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            pixels,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let output_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("hapcodec bc output"),
+            size: output_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("hapcodec bc readback"),
+            size: output_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("hapcodec bc compress bind group"),
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: output_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("hapcodec bc compress encoder"),
+            });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("hapcodec bc compress pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(
+                blocks_x.div_ceil(WORKGROUP_SIZE),
+                blocks_y.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+        encoder.copy_buffer_to_buffer(&output_buffer, 0, &readback_buffer, 0, output_size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = readback_buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        rx.recv()
+            .map_err(|_| Error::InternalThreadProblem)?
+            .map_err(|_| Error::InternalThreadProblem)?;
+
+        let data = slice.get_mapped_range().to_vec();
+        readback_buffer.unmap();
+        Ok(data)
+    }
+}
+
+impl BlockCompressor for GpuCompressor {
+    fn compress(
+        &self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        compression: PixelCompression,
+    ) -> Result<Vec<u8>, Error> {
+        GpuCompressor::compress(self, pixels, width, height, compression)
+    }
+}
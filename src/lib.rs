@@ -1,17 +1,49 @@
-use std::{
-    fmt::Debug,
-    io::{self, Read},
-};
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std::io::{self, Read};
 
+#[cfg(feature = "std")]
 use byteorder::{ReadBytesExt, LE};
-use itertools::Itertools;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "std")]
 use tracing::{debug, error, warn};
 
+#[cfg(feature = "std")]
+mod encoder;
+#[cfg(feature = "wgpu")]
+mod gpu_compress;
+mod reader;
+pub mod shaders;
+#[cfg(feature = "std")]
+mod streaming;
+
+#[cfg(feature = "std")]
+pub use encoder::{
+    compress_dxt1, compress_dxt5, compress_scaled_ycocg_dxt5, encode_frame, BlockCompressor,
+    EncodeOptions, Encoder, ScalarBlockCompressor,
+};
+#[cfg(feature = "wgpu")]
+pub use gpu_compress::GpuCompressor;
+pub use reader::{read_uncompressed_texture, Reader, ReaderError};
+#[cfg(feature = "std")]
+pub use streaming::{Decoded, StreamingDecoder};
+
+#[cfg(feature = "std")]
 const HAP_SECITON_CHUNK_SECOND_STAGE_COMPRESSOR_TABLE: u8 = 0x02;
+#[cfg(feature = "std")]
 const HAP_SECTION_CHUNK_SIZE_TABLE: u8 = 0x03;
+#[cfg(feature = "std")]
 const HAP_SECTION_CHUNK_OFFSET_TABLE: u8 = 0x04;
 
-pub type RawTexture = Vec<u8>;
+#[cfg(feature = "std")]
+pub type RawTexture = std::vec::Vec<u8>;
+#[cfg(not(feature = "std"))]
+pub type RawTexture = alloc::vec::Vec<u8>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PixelFormat {
@@ -24,10 +56,15 @@ pub enum PixelFormat {
     MultipleImages,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PixelCompression {
     DXT1BC1,
     DXT5BC3,
+    /// Hap Q's scaled-YCoCg color packing (see [`crate::shaders::SCALED_YCOCG_FS`])
+    /// compressed with the same DXT5/BC3 block layout as [`Self::DXT5BC3`] —
+    /// kept distinct because the color-space transform happens before block
+    /// compression, not inside it.
+    ScaledYCoCgDXT5BC3,
     BC7,
     RGTC1BC4,
     BC6U,
@@ -50,6 +87,15 @@ pub enum Texture {
     /// GL_COMPRESSED_RGB_BPTC_SIGNED_FLOAT_ARB
     RGBSignedFloat_BC6S(RawTexture),
     MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1(RawTexture, RawTexture),
+    /// "Hap Q Alpha": a [`Self::ScaledYCoCg_DXT5_BC3`] color plane plus a
+    /// separate [`Self::Alpha_RGTC1_BC4`] alpha plane, distinct from
+    /// [`Self::MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1`] so the decoder
+    /// can tell Hap Q Alpha (scaled-YCoCg color) apart from plain Hap Alpha
+    /// (unscaled RGBA color) dual-plane frames.
+    ScaledYCoCgWithAlpha {
+        color: RawTexture,
+        alpha: RawTexture,
+    },
 }
 
 impl Texture {
@@ -63,6 +109,7 @@ impl Texture {
             Self::RGBUnsignedFloat_BC6U(inner) => Some(inner),
             Self::RGBSignedFloat_BC6S(inner) => Some(inner),
             Self::MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1(_, _) => None,
+            Self::ScaledYCoCgWithAlpha { .. } => None,
         }
     }
 
@@ -76,6 +123,7 @@ impl Texture {
             Self::RGBUnsignedFloat_BC6U(inner) => Some(inner.as_ref()),
             Self::RGBSignedFloat_BC6S(inner) => Some(inner.as_ref()),
             Self::MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1(_, _) => None,
+            Self::ScaledYCoCgWithAlpha { .. } => None,
         }
     }
 }
@@ -87,8 +135,8 @@ pub enum OpenGLFormatId {
     Unsupported,
 }
 
-impl Debug for Texture {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for Texture {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let mut s = f.debug_struct("Texture");
         match self {
             Self::RGB_DXT1_BC1(inner) => s
@@ -134,6 +182,14 @@ impl Debug for Texture {
                 .field("size1", &inner1.len())
                 .field("size2", &inner2.len())
                 .finish(),
+            Self::ScaledYCoCgWithAlpha { color, alpha } => s
+                .field("color", &"ScaledYCoCg")
+                .field("alpha", &"Alpha")
+                .field("compression_color", &"DXT5/BC3")
+                .field("compression_alpha", &"BC4")
+                .field("size_color", &color.len())
+                .field("size_alpha", &alpha.len())
+                .finish(),
         }
     }
 }
@@ -150,6 +206,65 @@ impl Texture {
             Self::RGBUnsignedFloat_BC6U(_) => OpenGLFormatId::Single(0x8E8F),
             Self::RGBSignedFloat_BC6S(_) => OpenGLFormatId::Single(0x8E8E),
             Self::MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1(_, _) => OpenGLFormatId::Unsupported,
+            Self::ScaledYCoCgWithAlpha { .. } => OpenGLFormatId::Unsupported,
+        }
+    }
+
+    /// The single `GL_COMPRESSED_*` internal format to pass to
+    /// `glCompressedTexImage2D`, or `None` for variants
+    /// [`Self::opengl_pixelformat_id`] reports as `Unsupported` or
+    /// `Double` (dual-plane textures upload as two separate GL textures, one
+    /// per [`Self::opengl_pixelformat_id`] call on each plane, so there's no
+    /// single format to return here). [`Self::ScaledYCoCg_DXT5_BC3`] is the
+    /// exception: it uploads as plain DXT5 (`0x83F3`) and is unpacked by
+    /// [`crate::shaders::SCALED_YCOCG_FS`] at sample time.
+    pub fn gl_internal_format(&self) -> Option<gl::types::GLenum> {
+        if let Self::ScaledYCoCg_DXT5_BC3(_) = self {
+            return Some(0x83F3);
+        }
+        match self.opengl_pixelformat_id() {
+            OpenGLFormatId::Single(id) => Some(id),
+            OpenGLFormatId::Double(_, _) | OpenGLFormatId::Unsupported => None,
+        }
+    }
+
+    /// Number of bytes a `width`x`height` image compressed to this
+    /// variant's block format takes up, e.g. for sizing the buffer passed to
+    /// `glCompressedTexImage2D`, or `None` for the same dual-plane variants
+    /// [`Self::gl_internal_format`] returns `None` for — there's no single
+    /// buffer size for a texture that uploads as two separate GL textures,
+    /// one per plane (see [`Self::compressed_byte_len_per_plane`] instead).
+    /// Hap's bitstream doesn't carry image dimensions itself (that's the
+    /// surrounding container's job), so `width`/`height` have to come from
+    /// the caller rather than `self`.
+    pub fn compressed_byte_len(&self, width: u32, height: u32) -> Option<usize> {
+        let bytes_per_block = match self {
+            Self::RGB_DXT1_BC1(_) => 8,
+            Self::RGBA_DXT5_BC3(_) => 16,
+            Self::ScaledYCoCg_DXT5_BC3(_) => 16,
+            Self::RGBA_BC7(_) => 16,
+            Self::Alpha_RGTC1_BC4(_) => 8,
+            Self::RGBUnsignedFloat_BC6U(_) => 16,
+            Self::RGBSignedFloat_BC6S(_) => 16,
+            Self::MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1(_, _)
+            | Self::ScaledYCoCgWithAlpha { .. } => return None,
+        };
+        let blocks = (((width + 3) / 4) * ((height + 3) / 4)) as usize;
+        Some(blocks * bytes_per_block)
+    }
+
+    /// Per-plane byte sizes for the dual-plane variants
+    /// [`Self::compressed_byte_len`] returns `None` for: `(color_len,
+    /// alpha_len)`, where `color` is DXT5/BC3 (16 bytes/block) and `alpha` is
+    /// RGTC1/BC4 (8 bytes/block).
+    pub fn compressed_byte_len_per_plane(&self, width: u32, height: u32) -> Option<(usize, usize)> {
+        match self {
+            Self::MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1(_, _)
+            | Self::ScaledYCoCgWithAlpha { .. } => {
+                let blocks = (((width + 3) / 4) * ((height + 3) / 4)) as usize;
+                Some((blocks * 16, blocks * 8))
+            }
+            _ => None,
         }
     }
 }
@@ -167,6 +282,39 @@ impl Texture {
             Self::RGBUnsignedFloat_BC6U(_) => Some(Fmt::BptcUnsignedFloat3),
             Self::RGBSignedFloat_BC6S(_) => Some(Fmt::BptcSignedFloat3),
             Self::MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1(_, _) => None,
+            Self::ScaledYCoCgWithAlpha { .. } => None,
+        }
+    }
+}
+
+#[cfg(feature = "wgpu")]
+impl Texture {
+    /// Maps this variant to the `wgpu` BC `TextureFormat` it should be
+    /// uploaded as, or `None` when there's no direct equivalent (e.g. the
+    /// scaled-YCoCg and dual-plane variants, which need extra shader work
+    /// before they can be sampled as color).
+    pub fn wgpu_texture_format(&self) -> Option<wgpu::TextureFormat> {
+        match self {
+            Self::RGB_DXT1_BC1(_) => Some(wgpu::TextureFormat::Bc1RgbaUnorm),
+            Self::RGBA_DXT5_BC3(_) => Some(wgpu::TextureFormat::Bc3RgbaUnorm),
+            Self::ScaledYCoCg_DXT5_BC3(_) => None,
+            Self::RGBA_BC7(_) => Some(wgpu::TextureFormat::Bc7RgbaUnorm),
+            Self::Alpha_RGTC1_BC4(_) => Some(wgpu::TextureFormat::Bc4RUnorm),
+            Self::RGBUnsignedFloat_BC6U(_) => Some(wgpu::TextureFormat::Bc6hRgbUfloat),
+            Self::RGBSignedFloat_BC6S(_) => Some(wgpu::TextureFormat::Bc6hRgbFloat),
+            Self::MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1(_, _) => None,
+            Self::ScaledYCoCgWithAlpha { .. } => None,
+        }
+    }
+
+    /// Like [`Self::wgpu_texture_format`] but returns the sRGB-aware variant
+    /// where `wgpu` has one (only the unorm BC formats do).
+    pub fn wgpu_texture_format_srgb(&self) -> Option<wgpu::TextureFormat> {
+        match self {
+            Self::RGB_DXT1_BC1(_) => Some(wgpu::TextureFormat::Bc1RgbaUnormSrgb),
+            Self::RGBA_DXT5_BC3(_) => Some(wgpu::TextureFormat::Bc3RgbaUnormSrgb),
+            Self::RGBA_BC7(_) => Some(wgpu::TextureFormat::Bc7RgbaUnormSrgb),
+            _ => None,
         }
     }
 }
@@ -186,6 +334,10 @@ pub struct Header {
     pub second_stage_compressor: SecondStageCompressor,
 }
 
+/// The full decode/encode error type, available with the `std` feature
+/// (the default). Without `std`, only [`ReaderError`] (generic over the
+/// [`Reader`] implementation's own error type) is available.
+#[cfg(feature = "std")]
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("unknown compressor {0}")]
@@ -202,31 +354,37 @@ pub enum Error {
     InternalThreadProblem,
 }
 
+#[cfg(feature = "std")]
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Self {
         Self::Io(e)
     }
 }
 
+#[cfg(feature = "std")]
 struct RawSection {
-    size: u32,
+    size: u64,
     section_type: u8,
     header_size: usize,
 }
 
+#[cfg(feature = "std")]
 struct ChunkInfo {
     offset: usize,
     size: usize,
     compressor: SecondStageCompressor,
 }
 
+#[cfg(feature = "std")]
 fn parse_section_header<R: Read>(r: &mut R) -> io::Result<RawSection> {
     let section_size = r.read_u24::<byteorder::LE>()?;
     let section_type = r.read_u8()?;
+    // A zero short size is a sentinel for an 8-byte extended size field,
+    // used for sections too large for the 3-byte short form (>= 16MiB).
     let (section_size, header_size) = if section_size == 0 {
-        (r.read_u32::<byteorder::LE>()?, 4 + 4)
+        (r.read_u64::<byteorder::LE>()?, 4 + 8)
     } else {
-        (section_size, 4)
+        (section_size as u64, 4)
     };
     Ok(RawSection {
         size: section_size,
@@ -235,6 +393,7 @@ fn parse_section_header<R: Read>(r: &mut R) -> io::Result<RawSection> {
     })
 }
 
+#[cfg(feature = "std")]
 fn decode_second_stage_compressor(compressor: u8) -> Result<SecondStageCompressor, Error> {
     match compressor {
         0x0A => Ok(SecondStageCompressor::None),
@@ -249,6 +408,26 @@ fn decode_second_stage_compressor(compressor: u8) -> Result<SecondStageCompresso
     }
 }
 
+/// Inverse of [`wrap_single_texture`]: recovers the low nibble of the texture
+/// section type from a decoded [`Texture`] variant, for use by the encoder.
+#[cfg(feature = "std")]
+pub(crate) fn texture_format_nibble(texture: &Texture) -> Result<u8, Error> {
+    Ok(match texture {
+        Texture::RGB_DXT1_BC1(_) => 0x0b,
+        Texture::RGBA_DXT5_BC3(_) => 0x0e,
+        Texture::ScaledYCoCg_DXT5_BC3(_) => 0x0f,
+        Texture::RGBA_BC7(_) => 0x0c,
+        Texture::Alpha_RGTC1_BC4(_) => 0x01,
+        Texture::RGBUnsignedFloat_BC6U(_) => 0x02,
+        Texture::RGBSignedFloat_BC6S(_) => 0x03,
+        Texture::MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1(_, _) => {
+            return Err(Error::UnknownTextureFormat(0x0d))
+        }
+        Texture::ScaledYCoCgWithAlpha { .. } => return Err(Error::UnknownTextureFormat(0x0d)),
+    })
+}
+
+#[cfg(feature = "std")]
 fn wrap_single_texture(texture_format: u8, raw: RawTexture) -> Result<Texture, Error> {
     Ok(match texture_format & 0x0f {
         0x0b => Texture::RGB_DXT1_BC1(raw),
@@ -262,10 +441,10 @@ fn wrap_single_texture(texture_format: u8, raw: RawTexture) -> Result<Texture, E
     })
 }
 
-#[cfg(not(feature = "threadpool"))]
+#[cfg(all(feature = "std", not(feature = "threadpool")))]
 pub struct Decoder;
 
-#[cfg(not(feature = "threadpool"))]
+#[cfg(all(feature = "std", not(feature = "threadpool")))]
 impl Decoder {
     pub fn new() -> Self {
         Self
@@ -274,8 +453,8 @@ impl Decoder {
 
 #[cfg(feature = "threadpool")]
 pub struct Decoder {
-    tx: std::sync::mpsc::Sender<(uuid::Uuid, Vec<u8>)>,
-    rx: std::sync::mpsc::Receiver<(uuid::Uuid, Result<Vec<u8>, snap::Error>)>,
+    tx: std::sync::mpsc::Sender<(usize, Vec<u8>)>,
+    rx: std::sync::mpsc::Receiver<(usize, Result<Vec<u8>, snap::Error>)>,
 }
 
 #[cfg(feature = "threadpool")]
@@ -285,7 +464,7 @@ impl Decoder {
             sync::{mpsc, Arc, Mutex},
             thread::spawn,
         };
-        let (raw_tx, raw_rx) = mpsc::channel::<(uuid::Uuid, Vec<u8>)>();
+        let (raw_tx, raw_rx) = mpsc::channel::<(usize, Vec<u8>)>();
         let (decompressed_tx, decompressed_rx) = mpsc::channel();
         let raw_rx = Arc::new(Mutex::new(raw_rx));
         (0..thread_size).into_iter().for_each(|_| {
@@ -295,9 +474,9 @@ impl Decoder {
                 let mut decoder = snap::raw::Decoder::new();
                 loop {
                     match raw_rx.lock().unwrap().recv() {
-                        Ok((uuid, raw)) => {
+                        Ok((index, raw)) => {
                             let decompressed = decoder.decompress_vec(&raw);
-                            decompressed_tx.send((uuid, decompressed)).unwrap()
+                            decompressed_tx.send((index, decompressed)).unwrap()
                         }
                         Err(e) => error!("{}", e),
                     }
@@ -311,6 +490,7 @@ impl Decoder {
     }
 }
 
+#[cfg(feature = "std")]
 impl Decoder {
     fn decode_complex_instruction<R: Read>(
         &self,
@@ -382,7 +562,7 @@ impl Decoder {
             let mut buf = Vec::new();
             buf.resize(raw_section.size as usize - consumed_size, 0);
             r.read_exact(&mut buf)?;
-            #[cfg(not(feature = "threadpool"))]
+            #[cfg(not(any(feature = "threadpool", feature = "rayon")))]
             for chunk_info in chunk_infos {
                 let mut decoder = snap::raw::Decoder::new();
                 if chunk_info.compressor == SecondStageCompressor::Snappy {
@@ -394,44 +574,54 @@ impl Decoder {
                             .map_err(Error::Snappy)?,
                     );
                 } else {
-                    decoded_raw_data.append(&mut buf);
+                    decoded_raw_data.extend_from_slice(
+                        &buf[chunk_info.offset..chunk_info.offset + chunk_info.size],
+                    );
+                }
+            }
+            #[cfg(all(feature = "rayon", not(feature = "threadpool")))]
+            {
+                let decompressed: Vec<Vec<u8>> = chunk_infos
+                    .into_par_iter()
+                    .map(|chunk_info| {
+                        let chunk = &buf[chunk_info.offset..chunk_info.offset + chunk_info.size];
+                        if chunk_info.compressor == SecondStageCompressor::Snappy {
+                            snap::raw::Decoder::new()
+                                .decompress_vec(chunk)
+                                .map_err(Error::Snappy)
+                        } else {
+                            Ok(chunk.to_vec())
+                        }
+                    })
+                    .collect::<Result<_, _>>()?;
+                for mut chunk in decompressed {
+                    decoded_raw_data.append(&mut chunk);
                 }
             }
             #[cfg(feature = "threadpool")]
             {
-                let mut indices = Vec::new();
                 let mut buffer = Vec::new();
                 buffer.resize_with(chunk_infos.len(), Vec::new);
                 let mut queued_count = 0;
-                for chunk_info in chunk_infos {
-                    let id = uuid::Uuid::new_v4();
-                    indices.push(id);
+                for (index, chunk_info) in chunk_infos.into_iter().enumerate() {
                     if chunk_info.compressor == SecondStageCompressor::Snappy {
                         self.tx
                             .send((
-                                id,
+                                index,
                                 buf[chunk_info.offset..chunk_info.offset + chunk_info.size]
                                     .to_vec(),
                             ))
                             .map_err(|_| Error::InternalThreadProblem)?;
                         queued_count += 1;
                     } else {
-                        let (idx, _) = indices
-                            .iter()
-                            .find_position(|id_in_indices| id_in_indices == &&id)
-                            .unwrap();
-                        buffer[idx] =
+                        buffer[index] =
                             buf[chunk_info.offset..chunk_info.offset + chunk_info.size].to_vec();
                     }
                 }
                 for _ in 0..queued_count {
-                    let (id, decompressed) =
+                    let (index, decompressed) =
                         self.rx.recv().map_err(|_| Error::InternalThreadProblem)?;
-                    let (idx, _) = indices
-                        .iter()
-                        .find_position(|id_in_indices| id_in_indices == &&id)
-                        .unwrap();
-                    buffer[idx] = decompressed.map_err(Error::Snappy)?;
+                    buffer[index] = decompressed.map_err(Error::Snappy)?;
                 }
                 for mut buf in buffer {
                     decoded_raw_data.append(&mut buf);
@@ -466,12 +656,18 @@ impl Decoder {
                 let (raw, texture_format) = self.decode_texture(texture_section_header, r)?;
                 wrap_single_texture(texture_format, raw)
             } else {
-                let (dxt5, _) = self.decode_texture(texture_section_header, r)?;
+                let (color, color_format) = self.decode_texture(texture_section_header, r)?;
                 let texture_section_header = parse_section_header(r)?;
-                let (rgtc1, _) = self.decode_texture(texture_section_header, r)?;
-                Ok(Texture::MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1(
-                    dxt5, rgtc1,
-                ))
+                let (alpha, _) = self.decode_texture(texture_section_header, r)?;
+                if color_format == 0x0f {
+                    // Hap Q Alpha: scaled-YCoCg color plane.
+                    Ok(Texture::ScaledYCoCgWithAlpha { color, alpha })
+                } else {
+                    // Hap Alpha: plain RGBA color plane.
+                    Ok(Texture::MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1(
+                        color, alpha,
+                    ))
+                }
             }
         } else {
             let (raw, texture_format) = self.decode_texture(raw_section, r)?;
@@ -479,3 +675,168 @@ impl Decoder {
         }
     }
 }
+
+#[cfg(all(test, feature = "std", not(feature = "threadpool")))]
+mod tests {
+    use super::*;
+
+    /// Regression test for a bug where the `0xC0` complex path's sequential
+    /// (no `threadpool`/`rayon`) decode branch appended the *entire*
+    /// remaining read buffer instead of just the current chunk's slice for
+    /// any chunk stored raw (not Snappy-compressed) — corrupting every frame
+    /// that mixes compressed and uncompressed chunks.
+    #[test]
+    fn multi_chunk_decode_matches_single_chunk() {
+        let width = 32;
+        let height = 32;
+        let pixels: Vec<u8> = (0..(width * height * 4) as usize)
+            .map(|i| (i % 256) as u8)
+            .collect();
+        let compressed = encoder::compress_dxt1(&pixels, width, height);
+        let texture = Texture::RGB_DXT1_BC1(compressed.clone());
+
+        let single_chunk_opts = EncodeOptions {
+            chunk_count: 1,
+            second_stage_compressor: SecondStageCompressor::None,
+        };
+        let multi_chunk_opts = EncodeOptions {
+            chunk_count: 4,
+            second_stage_compressor: SecondStageCompressor::None,
+        };
+
+        let encoder = Encoder::new();
+        let single_chunk_frame = encoder.encode_frame(&texture, &single_chunk_opts).unwrap();
+        let multi_chunk_frame = encoder.encode_frame(&texture, &multi_chunk_opts).unwrap();
+
+        let decoder = Decoder::new();
+        let single_decoded = decoder
+            .decode_frame(&mut io::Cursor::new(single_chunk_frame))
+            .unwrap();
+        let multi_decoded = decoder
+            .decode_frame(&mut io::Cursor::new(multi_chunk_frame))
+            .unwrap();
+
+        let Texture::RGB_DXT1_BC1(single_raw) = single_decoded else {
+            panic!("expected RGB_DXT1_BC1");
+        };
+        let Texture::RGB_DXT1_BC1(multi_raw) = multi_decoded else {
+            panic!("expected RGB_DXT1_BC1");
+        };
+        assert_eq!(single_raw, compressed);
+        assert_eq!(multi_raw, compressed);
+    }
+
+    /// Same as [`multi_chunk_decode_matches_single_chunk`], but with
+    /// [`SecondStageCompressor::Snappy`] on genuinely repetitive (and thus
+    /// compressible) data, so every chunk actually takes the Snappy
+    /// decompression branch rather than falling back to stored-raw.
+    #[test]
+    fn multi_chunk_decode_with_snappy_chunks_matches_single_chunk() {
+        let width = 32;
+        let height = 32;
+        // A flat color compresses every DXT1 block identically, so Snappy
+        // finds plenty to compress.
+        let pixels: Vec<u8> = [10u8, 20, 30, 255]
+            .iter()
+            .copied()
+            .cycle()
+            .take((width * height * 4) as usize)
+            .collect();
+        let compressed = encoder::compress_dxt1(&pixels, width, height);
+        let texture = Texture::RGB_DXT1_BC1(compressed.clone());
+
+        let single_chunk_opts = EncodeOptions {
+            chunk_count: 1,
+            second_stage_compressor: SecondStageCompressor::Snappy,
+        };
+        let multi_chunk_opts = EncodeOptions {
+            chunk_count: 4,
+            second_stage_compressor: SecondStageCompressor::Snappy,
+        };
+
+        let encoder = Encoder::new();
+        let single_chunk_frame = encoder.encode_frame(&texture, &single_chunk_opts).unwrap();
+        let multi_chunk_frame = encoder.encode_frame(&texture, &multi_chunk_opts).unwrap();
+
+        let decoder = Decoder::new();
+        let single_decoded = decoder
+            .decode_frame(&mut io::Cursor::new(single_chunk_frame))
+            .unwrap();
+        let multi_decoded = decoder
+            .decode_frame(&mut io::Cursor::new(multi_chunk_frame))
+            .unwrap();
+
+        let Texture::RGB_DXT1_BC1(single_raw) = single_decoded else {
+            panic!("expected RGB_DXT1_BC1");
+        };
+        let Texture::RGB_DXT1_BC1(multi_raw) = multi_decoded else {
+            panic!("expected RGB_DXT1_BC1");
+        };
+        assert_eq!(single_raw, compressed);
+        assert_eq!(multi_raw, compressed);
+    }
+
+    /// `parse_section_header` must fall back to the 8-byte extended size
+    /// field when the 3-byte short size is the zero sentinel, for sections
+    /// too large for the short form to represent.
+    #[test]
+    fn parse_section_header_reads_extended_size() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&[0, 0, 0]); // zero short size: sentinel
+        bytes.push(0xA0 | 0x0b); // section type: stored, DXT1/BC1
+        bytes.extend_from_slice(&(0x0100_0000u64).to_le_bytes()); // extended size
+
+        let section = parse_section_header(&mut io::Cursor::new(bytes)).unwrap();
+        assert_eq!(section.size, 0x0100_0000);
+        assert_eq!(section.section_type, 0xA0 | 0x0b);
+        assert_eq!(section.header_size, 4 + 8);
+    }
+
+    /// A payload just under the 3-byte short-size limit (`write_section_header`'s
+    /// `size < 0x00FF_FFFF` cutoff) must still round-trip through the short
+    /// form, not the extended one.
+    #[test]
+    fn round_trip_frame_just_under_extended_size_threshold() {
+        let raw = vec![0xABu8; 0x00FF_FFFE];
+        let texture = Texture::RGB_DXT1_BC1(raw.clone());
+        let opts = EncodeOptions {
+            chunk_count: 1,
+            second_stage_compressor: SecondStageCompressor::None,
+        };
+        let frame = Encoder::new().encode_frame(&texture, &opts).unwrap();
+        // Short form: 4-byte header immediately followed by the payload.
+        assert_eq!(frame.len(), 4 + raw.len());
+
+        let decoded = Decoder::new()
+            .decode_frame(&mut io::Cursor::new(frame))
+            .unwrap();
+        let Texture::RGB_DXT1_BC1(decoded_raw) = decoded else {
+            panic!("expected RGB_DXT1_BC1");
+        };
+        assert_eq!(decoded_raw, raw);
+    }
+
+    /// A payload at/just over the same threshold must round-trip through the
+    /// 8-byte extended-size form instead.
+    #[test]
+    fn round_trip_frame_just_over_extended_size_threshold() {
+        let raw = vec![0xABu8; 0x00FF_FFFF];
+        let texture = Texture::RGB_DXT1_BC1(raw.clone());
+        let opts = EncodeOptions {
+            chunk_count: 1,
+            second_stage_compressor: SecondStageCompressor::None,
+        };
+        let frame = Encoder::new().encode_frame(&texture, &opts).unwrap();
+        // Extended form: 3-byte zero sentinel + 1-byte type + 8-byte size,
+        // followed by the payload.
+        assert_eq!(frame.len(), 4 + 8 + raw.len());
+
+        let decoded = Decoder::new()
+            .decode_frame(&mut io::Cursor::new(frame))
+            .unwrap();
+        let Texture::RGB_DXT1_BC1(decoded_raw) = decoded else {
+            panic!("expected RGB_DXT1_BC1");
+        };
+        assert_eq!(decoded_raw, raw);
+    }
+}
@@ -0,0 +1,86 @@
+//! An abstraction over a byte source, standing in for `std::io::Read`, so
+//! the section-header and uncompressed-texture parsing below can run in
+//! `no_std` + embedded contexts. With the default `std` feature enabled the
+//! full [`crate::Decoder`]/[`crate::Encoder`]/[`crate::StreamingDecoder`]
+//! API (and their `threadpool`, `snap`, `tracing` dependencies) is
+//! still available; with `std` disabled, only this module plus the core
+//! [`crate::Texture`]/[`crate::PixelFormat`]/[`crate::PixelCompression`]
+//! types are, and `byteorder` is the crate's only remaining dependency.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec;
+
+use crate::RawTexture;
+
+/// A byte source [`read_uncompressed_texture`] can pull from. Mirrors the
+/// subset of `std::io::Read` this crate actually needs.
+pub trait Reader {
+    type Error;
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> Reader for R {
+    type Error = std::io::Error;
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        std::io::Read::read_exact(self, buf)
+    }
+}
+
+/// Errors from the `no_std`-friendly parsing path, generic over the
+/// [`Reader`]'s own error type — in place of [`crate::Error`]'s
+/// `Io(io::Error)` variant, which needs `std`.
+#[derive(Debug)]
+pub enum ReaderError<E> {
+    Io(E),
+    UnknownTextureFormat(u8),
+    /// The section needs Snappy decompression (`0xB0`/`0xC0`) or is a
+    /// multi-texture container (`0x0d`); only [`crate::Decoder`] (which
+    /// needs `std`) handles those.
+    RequiresStd,
+}
+
+struct RawSection {
+    size: u64,
+    section_type: u8,
+}
+
+fn read_section_header<R: Reader>(r: &mut R) -> Result<RawSection, ReaderError<R::Error>> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf).map_err(ReaderError::Io)?;
+    let size = u32::from_le_bytes([buf[0], buf[1], buf[2], 0]);
+    let section_type = buf[3];
+    if size != 0 {
+        return Ok(RawSection {
+            size: size as u64,
+            section_type,
+        });
+    }
+    // Zero short size is the sentinel for an 8-byte extended size field,
+    // used for sections too large for the 3-byte short form.
+    let mut ext = [0u8; 8];
+    r.read_exact(&mut ext).map_err(ReaderError::Io)?;
+    Ok(RawSection {
+        size: u64::from_le_bytes(ext),
+        section_type,
+    })
+}
+
+/// Parses a top-level Hap section header and, if it's the `0xA0` (stored,
+/// uncompressed) fast path, returns its raw texture bytes and pixel-format
+/// nibble (see [`crate::Texture`]). Every other storage mode needs Snappy
+/// decompression or nested sections and returns
+/// [`ReaderError::RequiresStd`].
+pub fn read_uncompressed_texture<R: Reader>(
+    r: &mut R,
+) -> Result<(RawTexture, u8), ReaderError<R::Error>> {
+    let section = read_section_header(r)?;
+    if section.section_type & 0xF0 != 0xA0 {
+        return Err(ReaderError::RequiresStd);
+    }
+    let mut raw = vec![0; section.size as usize];
+    r.read_exact(&mut raw).map_err(ReaderError::Io)?;
+    Ok((raw, section.section_type & 0x0F))
+}
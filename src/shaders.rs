@@ -0,0 +1,32 @@
+//! GLSL fragment shaders for reconstructing pixel formats that can't be
+//! sampled directly from the decoded [`crate::Texture`] — currently just the
+//! scaled-YCoCg packing used by `ScaledYCoCg_DXT5_BC3` ("Hap Q"), where the
+//! chroma channels are multiplied by a per-pixel scale factor stored in the
+//! blue channel (luma lives in alpha) before being DXT5/BC3-compressed, and
+//! must be divided back out at sample time.
+
+/// Reconstructs RGB from a `ScaledYCoCg_DXT5_BC3` ("Hap Q") texture sampled
+/// into `scaled_ycocg`. Divides Co/Cg by the factor packed into blue (`B`)
+/// to undo the encoder's scaling before the standard YCoCg -> RGB conversion; luma (`Y`) is read
+/// straight from alpha. The blue channel is only a scale carrier and must
+/// never be treated as color.
+pub const SCALED_YCOCG_FS: &str = r#"
+#version 110
+
+uniform sampler2D image;
+varying vec2 uv;
+
+void main() {
+    vec4 scaled_ycocg = texture2D(image, uv);
+    float y = scaled_ycocg.a;
+    float s = (scaled_ycocg.b * 255.0 / 8.0) + 1.0;
+    float co = (scaled_ycocg.r - 0.5) / s;
+    float cg = (scaled_ycocg.g - 0.5) / s;
+
+    float r = y + co - cg;
+    float g = y + cg;
+    float b = y - co - cg;
+
+    gl_FragColor = vec4(r, g, b, 1.0);
+}
+"#;
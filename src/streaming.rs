@@ -0,0 +1,457 @@
+//! Push-based incremental decoding, in the style of the PNG crate's
+//! `StreamingDecoder`: instead of a blocking [`std::io::Read`] that must be
+//! able to deliver a whole frame at once, [`StreamingDecoder`] is fed
+//! arbitrary byte slices (as they arrive from a socket or demuxer) and
+//! reports how many bytes it consumed plus an optional [`Decoded`] event.
+//! Partial section headers and partial chunk payloads are buffered
+//! internally across calls, so no call ever needs more bytes than it is
+//! given.
+
+use byteorder::{ByteOrder, LE};
+
+use crate::{
+    decode_second_stage_compressor, wrap_single_texture, ChunkInfo, Error, RawTexture,
+    SecondStageCompressor, Texture, HAP_SECITON_CHUNK_SECOND_STAGE_COMPRESSOR_TABLE,
+    HAP_SECTION_CHUNK_OFFSET_TABLE, HAP_SECTION_CHUNK_SIZE_TABLE,
+};
+
+/// Events emitted by [`StreamingDecoder::feed`] as enough bytes arrive.
+#[derive(Debug)]
+pub enum Decoded {
+    /// The frame's top-level section header was fully parsed (nested
+    /// section headers emit no event).
+    SectionHeader { section_type: u8, size: usize },
+    /// The `0xC0` decode-instructions container (compressor/size/offset
+    /// tables) has been fully parsed; chunk payload bytes follow.
+    ChunkTableComplete,
+    /// A complete texture has been decoded.
+    TextureReady(Texture),
+}
+
+/// Incrementally reads a 3-byte size + 1-byte type section header, including
+/// the 8-byte extended size that replaces a `0` short size.
+#[derive(Debug, Default)]
+struct HeaderReader {
+    buf: [u8; 4],
+    filled: usize,
+    short_type: Option<u8>,
+    ext_buf: [u8; 8],
+    ext_filled: usize,
+}
+
+struct ParsedHeader {
+    section_type: u8,
+    size: usize,
+    header_size: usize,
+}
+
+impl HeaderReader {
+    fn feed(&mut self, data: &[u8]) -> (usize, Option<ParsedHeader>) {
+        let mut consumed = 0;
+        if self.short_type.is_none() {
+            while self.filled < 4 && consumed < data.len() {
+                self.buf[self.filled] = data[consumed];
+                self.filled += 1;
+                consumed += 1;
+            }
+            if self.filled < 4 {
+                return (consumed, None);
+            }
+            let size = LE::read_u24(&self.buf[0..3]);
+            let section_type = self.buf[3];
+            if size != 0 {
+                return (
+                    consumed,
+                    Some(ParsedHeader {
+                        section_type,
+                        size: size as usize,
+                        header_size: 4,
+                    }),
+                );
+            }
+            self.short_type = Some(section_type);
+        }
+        while self.ext_filled < 8 && consumed < data.len() {
+            self.ext_buf[self.ext_filled] = data[consumed];
+            self.ext_filled += 1;
+            consumed += 1;
+        }
+        if self.ext_filled < 8 {
+            return (consumed, None);
+        }
+        let size = LE::read_u64(&self.ext_buf);
+        (
+            consumed,
+            Some(ParsedHeader {
+                section_type: self.short_type.unwrap(),
+                size: size as usize,
+                header_size: 4 + 8,
+            }),
+        )
+    }
+}
+
+enum ComplexKind {
+    /// Reading the container's own header (its size bounds the three
+    /// instruction-table entries that follow).
+    ContainerHeader(HeaderReader),
+    /// Reading the header of the next instruction-table entry.
+    EntryHeader(HeaderReader, usize),
+    /// Buffering the body of the current instruction-table entry.
+    EntryBody {
+        section_type: u8,
+        target_size: usize,
+        buf: Vec<u8>,
+        container_remaining: usize,
+        header_size: usize,
+    },
+    /// Instruction tables fully parsed; buffering the raw chunk data that
+    /// follows, per the chunk size/offset tables.
+    ChunkData { chunks: Vec<ChunkInfo>, buf: Vec<u8> },
+}
+
+enum SectionKind {
+    /// `0xA0` (stored) or `0xB0` (whole-section Snappy) single chunk.
+    Simple { buf: Vec<u8> },
+    Complex {
+        kind: ComplexKind,
+        compressors: Vec<u8>,
+        sizes: Vec<u32>,
+        offsets: Vec<u32>,
+    },
+}
+
+/// Decodes one texture section (the `0xA0`/`0xB0`/`0xC0` payload that
+/// follows a section header) incrementally.
+struct SectionDecoder {
+    section_type: u8,
+    size: usize,
+    kind: SectionKind,
+}
+
+enum SectionEvent {
+    None,
+    ChunkTableComplete,
+    Done(RawTexture, u8),
+}
+
+impl SectionDecoder {
+    fn new(section_type: u8, size: usize) -> Self {
+        let kind = if section_type & 0xF0 == 0xC0 {
+            SectionKind::Complex {
+                kind: ComplexKind::ContainerHeader(HeaderReader::default()),
+                compressors: Vec::new(),
+                sizes: Vec::new(),
+                offsets: Vec::new(),
+            }
+        } else {
+            SectionKind::Simple { buf: Vec::new() }
+        };
+        Self {
+            section_type,
+            size,
+            kind,
+        }
+    }
+
+    fn finish_simple(section_type: u8, buf: Vec<u8>) -> Result<(RawTexture, u8), Error> {
+        let raw = if section_type & 0xF0 == 0xB0 {
+            snap::raw::Decoder::new()
+                .decompress_vec(&buf)
+                .map_err(Error::Snappy)?
+        } else {
+            buf
+        };
+        Ok((raw, section_type & 0x0F))
+    }
+
+    fn feed(&mut self, data: &[u8]) -> Result<(usize, SectionEvent), Error> {
+        match &mut self.kind {
+            SectionKind::Simple { buf } => {
+                let need = self.size - buf.len();
+                let take = need.min(data.len());
+                buf.extend_from_slice(&data[..take]);
+                if buf.len() == self.size {
+                    let (raw, format) =
+                        Self::finish_simple(self.section_type, std::mem::take(buf))?;
+                    Ok((take, SectionEvent::Done(raw, format)))
+                } else {
+                    Ok((take, SectionEvent::None))
+                }
+            }
+            SectionKind::Complex {
+                kind,
+                compressors,
+                sizes,
+                offsets,
+            } => Self::feed_complex(self.section_type, kind, compressors, sizes, offsets, data),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn feed_complex(
+        section_type: u8,
+        kind: &mut ComplexKind,
+        compressors: &mut Vec<u8>,
+        sizes: &mut Vec<u32>,
+        offsets: &mut Vec<u32>,
+        data: &[u8],
+    ) -> Result<(usize, SectionEvent), Error> {
+        match kind {
+            ComplexKind::ContainerHeader(header) => {
+                let (consumed, parsed) = header.feed(data);
+                if let Some(parsed) = parsed {
+                    *kind = ComplexKind::EntryHeader(HeaderReader::default(), parsed.size);
+                }
+                Ok((consumed, SectionEvent::None))
+            }
+            ComplexKind::EntryHeader(header, container_remaining) => {
+                let (consumed, parsed) = header.feed(data);
+                if let Some(parsed) = parsed {
+                    *kind = ComplexKind::EntryBody {
+                        section_type: parsed.section_type,
+                        target_size: parsed.size,
+                        buf: Vec::with_capacity(parsed.size),
+                        container_remaining: *container_remaining,
+                        header_size: parsed.header_size,
+                    };
+                }
+                Ok((consumed, SectionEvent::None))
+            }
+            ComplexKind::EntryBody {
+                section_type: entry_type,
+                target_size,
+                buf,
+                container_remaining,
+                header_size,
+            } => {
+                // The entry's declared size bounds its body; completion of
+                // the whole container is driven by how many bytes of it
+                // remain, matching `decode_complex_instruction`.
+                let take = (*target_size - buf.len()).min(data.len());
+                buf.extend_from_slice(&data[..take]);
+                if buf.len() == *target_size {
+                    match *entry_type {
+                        HAP_SECITON_CHUNK_SECOND_STAGE_COMPRESSOR_TABLE => {
+                            for byte in buf.iter() {
+                                decode_second_stage_compressor(*byte)?;
+                            }
+                            *compressors = std::mem::take(buf);
+                        }
+                        HAP_SECTION_CHUNK_SIZE_TABLE => {
+                            *sizes = buf.chunks(4).map(LE::read_u32).collect();
+                        }
+                        HAP_SECTION_CHUNK_OFFSET_TABLE => {
+                            *offsets = buf.chunks(4).map(LE::read_u32).collect();
+                        }
+                        _ => (),
+                    }
+                    let remaining = container_remaining.saturating_sub(*header_size + *target_size);
+                    if remaining == 0 {
+                        let mut chunks = Vec::with_capacity(sizes.len());
+                        let mut offset_subtotal = 0u32;
+                        for idx in 0..sizes.len() {
+                            let offset = if offsets.is_empty() {
+                                offset_subtotal
+                            } else {
+                                offsets[idx]
+                            } as usize;
+                            offset_subtotal += sizes[idx];
+                            let compressor = decode_second_stage_compressor(
+                                *compressors.get(idx).ok_or(Error::UnknownCompressor(0))?,
+                            )?;
+                            chunks.push(ChunkInfo {
+                                offset,
+                                size: sizes[idx] as usize,
+                                compressor,
+                            });
+                        }
+                        *kind = ComplexKind::ChunkData {
+                            chunks,
+                            buf: Vec::new(),
+                        };
+                        return Ok((take, SectionEvent::ChunkTableComplete));
+                    }
+                    *kind = ComplexKind::EntryHeader(HeaderReader::default(), remaining);
+                }
+                Ok((take, SectionEvent::None))
+            }
+            ComplexKind::ChunkData { chunks, buf } => {
+                // The chunk size/offset tables fully determine how many raw
+                // bytes follow the instructions container.
+                let total_chunk_bytes: usize = chunks.iter().map(|c| c.offset + c.size).max().unwrap_or(0);
+                let need = total_chunk_bytes.saturating_sub(buf.len()).min(data.len());
+                buf.extend_from_slice(&data[..need]);
+                if buf.len() >= total_chunk_bytes {
+                    let mut decoded = Vec::new();
+                    for chunk in chunks.iter() {
+                        let slice = &buf[chunk.offset..chunk.offset + chunk.size];
+                        if chunk.compressor == SecondStageCompressor::Snappy {
+                            decoded.extend_from_slice(
+                                &snap::raw::Decoder::new()
+                                    .decompress_vec(slice)
+                                    .map_err(Error::Snappy)?,
+                            );
+                        } else {
+                            decoded.extend_from_slice(slice);
+                        }
+                    }
+                    Ok((need, SectionEvent::Done(decoded, section_type & 0x0F)))
+                } else {
+                    Ok((need, SectionEvent::None))
+                }
+            }
+        }
+    }
+}
+
+enum Phase {
+    Header(HeaderReader),
+    Section(SectionDecoder),
+    MultiFirstHeader { outer_remaining: usize, header: HeaderReader },
+    MultiFirst { section: SectionDecoder },
+    MultiSecondHeader {
+        first: RawTexture,
+        first_format: u8,
+        header: HeaderReader,
+    },
+    MultiSecond {
+        first: RawTexture,
+        first_format: u8,
+        section: SectionDecoder,
+    },
+    Done,
+}
+
+/// A state-machine Hap decoder you feed arbitrary byte slices. See the
+/// module docs for the overall design.
+pub struct StreamingDecoder {
+    phase: Phase,
+}
+
+impl Default for StreamingDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamingDecoder {
+    pub fn new() -> Self {
+        Self {
+            phase: Phase::Header(HeaderReader::default()),
+        }
+    }
+
+    /// Feeds `data` to the decoder, returning how many leading bytes of
+    /// `data` were consumed and, if a meaningful event occurred, the event
+    /// itself. Call this again with the remainder of `data` (or new data)
+    /// until all input is consumed; after `TextureReady` the decoder is done
+    /// and further bytes are ignored.
+    pub fn feed(&mut self, data: &[u8]) -> Result<(usize, Option<Decoded>), Error> {
+        match &mut self.phase {
+            Phase::Done => Ok((0, None)),
+            Phase::Header(header) => {
+                let (consumed, parsed) = header.feed(data);
+                if let Some(parsed) = parsed {
+                    let event = Decoded::SectionHeader {
+                        section_type: parsed.section_type,
+                        size: parsed.size,
+                    };
+                    self.phase = if parsed.section_type == 0x0d {
+                        Phase::MultiFirstHeader {
+                            outer_remaining: parsed.size,
+                            header: HeaderReader::default(),
+                        }
+                    } else {
+                        Phase::Section(SectionDecoder::new(parsed.section_type, parsed.size))
+                    };
+                    Ok((consumed, Some(event)))
+                } else {
+                    Ok((consumed, None))
+                }
+            }
+            Phase::Section(section) => {
+                let (consumed, event) = section.feed(data)?;
+                match event {
+                    SectionEvent::None => Ok((consumed, None)),
+                    SectionEvent::ChunkTableComplete => Ok((consumed, Some(Decoded::ChunkTableComplete))),
+                    SectionEvent::Done(raw, format) => {
+                        self.phase = Phase::Done;
+                        Ok((
+                            consumed,
+                            Some(Decoded::TextureReady(wrap_single_texture(format, raw)?)),
+                        ))
+                    }
+                }
+            }
+            Phase::MultiFirstHeader { outer_remaining, header } => {
+                let (consumed, parsed) = header.feed(data);
+                if let Some(parsed) = parsed {
+                    let solo = parsed.header_size + parsed.size == *outer_remaining;
+                    let section = SectionDecoder::new(parsed.section_type, parsed.size);
+                    self.phase = if solo {
+                        Phase::Section(section)
+                    } else {
+                        Phase::MultiFirst { section }
+                    };
+                }
+                Ok((consumed, None))
+            }
+            Phase::MultiFirst { section } => {
+                let (consumed, event) = section.feed(data)?;
+                match event {
+                    SectionEvent::None => Ok((consumed, None)),
+                    SectionEvent::ChunkTableComplete => Ok((consumed, Some(Decoded::ChunkTableComplete))),
+                    SectionEvent::Done(raw, format) => {
+                        self.phase = Phase::MultiSecondHeader {
+                            first: raw,
+                            first_format: format,
+                            header: HeaderReader::default(),
+                        };
+                        Ok((consumed, None))
+                    }
+                }
+            }
+            Phase::MultiSecondHeader {
+                first,
+                first_format,
+                header,
+            } => {
+                let (consumed, parsed) = header.feed(data);
+                if let Some(parsed) = parsed {
+                    let first = std::mem::take(first);
+                    self.phase = Phase::MultiSecond {
+                        first,
+                        first_format: *first_format,
+                        section: SectionDecoder::new(parsed.section_type, parsed.size),
+                    };
+                }
+                Ok((consumed, None))
+            }
+            Phase::MultiSecond {
+                first,
+                first_format,
+                section,
+            } => {
+                let (consumed, event) = section.feed(data)?;
+                match event {
+                    SectionEvent::None => Ok((consumed, None)),
+                    SectionEvent::ChunkTableComplete => Ok((consumed, Some(Decoded::ChunkTableComplete))),
+                    SectionEvent::Done(second, _format) => {
+                        let first = std::mem::take(first);
+                        let texture = if *first_format == 0x0f {
+                            Texture::ScaledYCoCgWithAlpha {
+                                color: first,
+                                alpha: second,
+                            }
+                        } else {
+                            Texture::MultipleImages_ScaledYCoCg_DXT5_Alpha_RGTC1(first, second)
+                        };
+                        self.phase = Phase::Done;
+                        Ok((consumed, Some(Decoded::TextureReady(texture))))
+                    }
+                }
+            }
+        }
+    }
+}